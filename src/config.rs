@@ -2,8 +2,14 @@
 
 extern crate log;
 extern crate toml;
+extern crate chrono;
+extern crate glob;
+extern crate lazy_static;
 
-use std::path::PathBuf;
+use chrono::NaiveDateTime;
+use std::env;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 use std::convert::TryFrom;
 use regex::Regex;
 use error::*;
@@ -15,13 +21,59 @@ use getset::Getters;
 
 /// Holds data for one Log-File.
 /// Used for deserialization only
+///
+/// `regex` is kept as the raw pattern string rather than compiled here, so
+/// that a malformed pattern can be reported with the offending `alias`
+/// attached instead of surfacing as an opaque TOML parse error.
 #[derive(Clone, Debug, Deserialize)]
 pub struct LogItemDeser {
-    file : String,
-
-    #[serde(with="serde_regex")]
-    regex : Regex,
+    /// A single path, a single glob pattern, or a list of either, all of
+    /// which are resolved into concrete files sharing this item's
+    /// `regex`/`alias`/`aliases`.
+    file : LogItemFileDeser,
+    regex : String,
     alias : String,
+
+    /// Which capture group of `regex` holds the timestamp, by name or by
+    /// its 1-based index. Defaults to index `1` (the historical "second
+    /// capture is always the timestamp" behaviour) when omitted.
+    #[serde(default)]
+    timestamp_group : Option<TimestampGroupDeser>,
+
+    /// `strftime`-style pattern used to parse the timestamp capture.
+    /// Without it, the capture is expected to already be an epoch
+    /// timestamp in milliseconds.
+    #[serde(default)]
+    timestamp_format : Option<String>,
+}
+
+/// Identifies a capture group either by name or by its 1-based index.
+/// Used for deserialization only.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum TimestampGroupDeser {
+    Name(String),
+    Index(usize),
+}
+
+/// A `LogItem`'s `file` key, either a single path/glob or a list of them.
+/// Used for deserialization only; kept separate from the single-`String`
+/// form so that existing configs with `file = "..."` keep working
+/// unchanged.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum LogItemFileDeser {
+    Single(String),
+    Many(Vec<String>),
+}
+
+impl LogItemFileDeser {
+    fn into_patterns(self) -> Vec<String> {
+        match self {
+            LogItemFileDeser::Single(s) => vec![s],
+            LogItemFileDeser::Many(v) => v,
+        }
+    }
 }
 
 /// Used for deserialization only
@@ -45,25 +97,169 @@ impl ConfigDeser {
                 debug!("Config = {:?}", obj);
                 obj
             })
-            .map_err(|_| ErrorKind::ConfigParseError(path).into())
+            .map_err(|err| ErrorKind::ParseToml(path.display().to_string(), err).into())
+    }
+
+    fn into_items(self) -> Vec<LogItemDeser> {
+        self.item
     }
+}
+
+lazy_static::lazy_static! {
+    /// Matches `${VAR}` and `$VAR` tokens. Compiled once and reused across
+    /// every `expand_env_vars` call, since config (re)loads can call it
+    /// once per `file` pattern, per item, per reload.
+    static ref ENV_VAR_PATTERN : Regex = Regex::new(
+        r"\$\{(?P<braced>[A-Za-z_][A-Za-z0-9_]*)\}|\$(?P<bare>[A-Za-z_][A-Za-z0-9_]*)"
+    ).expect("static env-var regex is valid");
+}
+
+/// Expands `${VAR}` and `$VAR` tokens in `value` using the current process
+/// environment, so a deployment can point `file` at a host-specific path
+/// without editing the TOML itself.
+fn expand_env_vars(value: &str) -> String {
+    ENV_VAR_PATTERN.replace_all(value, |caps: &regex::Captures| {
+        let name = caps.name("braced").or_else(|| caps.name("bare")).unwrap().as_str();
+        match env::var(name) {
+            Ok(v) => v,
+            Err(_) => {
+                warn!("environment variable '{}' referenced in configuration is not set", name);
+                caps.get(0).unwrap().as_str().to_string()
+            },
+        }
+    }).into_owned()
+}
+
+/// Expands `patterns` (after env-var substitution) into the concrete,
+/// deduplicated set of files they refer to. A pattern that is a glob
+/// (`app-*.log`) is resolved against the filesystem; a pattern with no
+/// glob metacharacters and no current matches is kept as a literal path,
+/// so a not-yet-created rotation target stays part of the item; it is
+/// only fingerprinted and watched once it exists and a reload happens.
+fn expand_file_patterns(alias: &str, patterns: Vec<String>) -> Result<Vec<String>> {
+    let mut files : Vec<String> = Vec::new();
+
+    for pattern in patterns {
+        let expanded = expand_env_vars(&pattern);
+        let matches = glob::glob(&expanded)
+            .map_err(|err| ErrorKind::InvalidGlob(alias.to_string(), expanded.clone(), err.to_string()))?;
 
-    fn get_items(&self) -> &Vec<LogItemDeser> {
-        &self.item
+        let mut found = false;
+        for entry in matches {
+            match entry {
+                Ok(path) => {
+                    found = true;
+                    files.push(path.display().to_string());
+                },
+                Err(err) => warn!("could not read glob entry for item '{}': {}", alias, err),
+            }
+        }
+
+        if !found {
+            if is_glob_pattern(&expanded) {
+                debug!("glob pattern '{}' for item '{}' currently has no matches", expanded, alias);
+            } else {
+                // A plain path has no file to fingerprint yet (e.g. a
+                // not-yet-created rotation target); keep it as a literal
+                // so a reload after it appears can fingerprint and watch it.
+                files.push(expanded);
+            }
+        }
     }
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+/// Whether `pattern` contains any glob metacharacters, as opposed to
+/// being a plain path.
+fn is_glob_pattern(pattern: &str) -> bool {
+    pattern.chars().any(|c| matches!(c, '*' | '?' | '[' | ']'))
 }
 
 //------------------------------------//
 //  struct to access data later on    //
 //------------------------------------//
 
+/// Identifies a capture group either by name or by its 1-based index.
+#[derive(Clone, Debug)]
+pub enum TimestampGroup {
+    Name(String),
+    Index(usize),
+}
+
+impl From<TimestampGroupDeser> for TimestampGroup {
+    fn from(deser: TimestampGroupDeser) -> Self {
+        match deser {
+            TimestampGroupDeser::Name(name) => TimestampGroup::Name(name),
+            TimestampGroupDeser::Index(idx) => TimestampGroup::Index(idx),
+        }
+    }
+}
+
+/// How to turn the timestamp capture of a matched log line into
+/// epoch-millisecond metric times for Grafana.
+#[derive(Clone, Debug)]
+pub enum TimestampStrategy {
+    /// No `timestamp_format` was configured: the capture is expected to
+    /// already hold an epoch timestamp in milliseconds. Preserves the
+    /// historical "second capture is always the timestamp" behaviour.
+    Raw { group : TimestampGroup },
+
+    /// The capture is parsed with an explicit `strftime` pattern.
+    Parsed { group : TimestampGroup, format : String },
+}
+
+/// Checks that `group` refers to an existing, usable capture group of
+/// `regex`, returning a config error naming `alias` otherwise. Index `0`
+/// (the whole match) is never a usable timestamp capture.
+fn validate_timestamp_group(regex: &Regex, group: &TimestampGroup, alias: &str) -> Result<()> {
+    let problem = match group {
+        TimestampGroup::Name(name) => {
+            if regex.capture_names().flatten().any(|n| n == name.as_str()) {
+                None
+            } else {
+                Some(format!("capture group named '{}' does not exist in the item's regex", name))
+            }
+        },
+        TimestampGroup::Index(0) => {
+            Some("capture group 0 is the whole match, not a usable timestamp capture".to_string())
+        },
+        TimestampGroup::Index(idx) => {
+            if *idx < regex.captures_len() {
+                None
+            } else {
+                Some(format!("capture group index {} does not exist in the item's regex", idx))
+            }
+        },
+    };
+
+    match problem {
+        None => Ok(()),
+        Some(message) => Err(ErrorKind::InvalidTimestampConfig(alias.to_string(), message).into()),
+    }
+}
+
+/// Whether capture group `idx` (possibly named `name`) is the one
+/// configured to hold the timestamp, and should therefore be excluded
+/// from the metric-name capture groups.
+fn is_timestamp_group(group: &TimestampGroup, idx: usize, name: Option<&str>) -> bool {
+    match group {
+        TimestampGroup::Name(configured) => name == Some(configured.as_str()),
+        TimestampGroup::Index(configured) => *configured == idx,
+    }
+}
+
 /// The deserialized Item would nearly always require some operation on its
 /// contents to use it, so we do those operations beforehand and only access
 /// the useful data from main().
 #[derive(Getters)]
 pub struct LogItem {
+    /// The concrete files this item reads from, resolved from its
+    /// configured path(s)/glob(s) at load time.
     #[getset(get = "pub")]
-    file : String,
+    files : Vec<String>,
 
     #[getset(get = "pub")]
     regex : Regex,
@@ -76,6 +272,41 @@ pub struct LogItem {
 
     #[getset(get = "pub")]
     aliases : Vec<String>,
+
+    #[getset(get = "pub")]
+    timestamp_strategy : TimestampStrategy,
+}
+
+impl LogItem {
+
+    /// Turns the regex capture holding the timestamp into epoch
+    /// milliseconds, according to this item's `timestamp_strategy`.
+    pub fn timestamp_millis(&self, caps: &regex::Captures) -> Result<i64> {
+        match &self.timestamp_strategy {
+            TimestampStrategy::Raw { group } => {
+                let raw = capture_for_group(caps, group)
+                    .ok_or_else(|| Error::from("matched line has no timestamp capture"))?;
+
+                raw.parse::<i64>()
+                    .chain_err(|| format!("timestamp capture '{}' is not a valid epoch-millisecond integer", raw))
+            },
+            TimestampStrategy::Parsed { group, format } => {
+                let raw = capture_for_group(caps, group)
+                    .ok_or_else(|| Error::from("matched line has no timestamp capture"))?;
+
+                NaiveDateTime::parse_from_str(raw, format)
+                    .chain_err(|| format!("timestamp '{}' does not match format '{}'", raw, format))
+                    .map(|dt| dt.timestamp_millis())
+            },
+        }
+    }
+}
+
+fn capture_for_group<'t>(caps: &'t regex::Captures, group: &TimestampGroup) -> Option<&'t str> {
+    match group {
+        TimestampGroup::Name(name) => caps.name(name).map(|m| m.as_str()),
+        TimestampGroup::Index(idx) => caps.get(*idx).map(|m| m.as_str()),
+    }
 }
 
 impl TryFrom<LogItemDeser> for LogItem {
@@ -83,13 +314,31 @@ impl TryFrom<LogItemDeser> for LogItem {
 
     /// Transforms a LogItemDeser into a more immediately usable LogItem
     fn try_from(lid : LogItemDeser) -> std::result::Result<LogItem, Self::Error> {
-        // first capture is the whole match and nameless
-        // second capture is always the timestamp
-        let cnames : Vec<String> = lid.regex
+        let regex = Regex::new(&lid.regex)
+            .map_err(|err| ErrorKind::InvalidRegex(lid.alias.clone(), err.to_string()))?;
+
+        // An explicitly configured `timestamp_group`/`timestamp_format` is
+        // validated against the regex; without either, index 1 (the
+        // historical "second capture is always the timestamp" position) is
+        // assumed but left unvalidated, so a regex with no extra capture
+        // groups still loads exactly as it did before timestamps were
+        // configurable.
+        let timestamp_configured = lid.timestamp_group.is_some() || lid.timestamp_format.is_some();
+        let timestamp_group = lid.timestamp_group.map(TimestampGroup::from).unwrap_or(TimestampGroup::Index(1));
+        if timestamp_configured {
+            validate_timestamp_group(&regex, &timestamp_group, &lid.alias)?;
+        }
+
+        // capture 0 is the whole match and nameless; the configured (or
+        // defaulted) timestamp group is excluded too, so it is never
+        // double-reported as a bogus metric alias.
+        let cnames : Vec<String> = regex
             .capture_names()
-            .skip(2)
-            .filter_map(|n| n)
-            .map(|n| String::from(n))
+            .enumerate()
+            .skip(1)
+            .filter(|(idx, name)| !is_timestamp_group(&timestamp_group, *idx, *name))
+            .filter_map(|(_, name)| name)
+            .map(String::from)
             .collect();
         debug!("capture names: {:?}", cnames);
 
@@ -105,13 +354,21 @@ impl TryFrom<LogItemDeser> for LogItem {
         }
         debug!("aliases: {:?}", als);
 
+        let timestamp_strategy = match lid.timestamp_format {
+            Some(format) => TimestampStrategy::Parsed { group : timestamp_group, format },
+            None => TimestampStrategy::Raw { group : timestamp_group },
+        };
+
+        let files = expand_file_patterns(&lid.alias, lid.file.into_patterns())?;
+
         Ok(
             LogItem {
-                file : lid.file,
-                regex : lid.regex,
+                files,
+                regex,
                 alias: lid.alias,
                 capture_names : cnames,
-                aliases : als
+                aliases : als,
+                timestamp_strategy,
             }
         )
     }
@@ -130,13 +387,29 @@ pub struct Config {
 impl Config {
 
     /// Lets serde do the deserialization, and transforms the given data
-    /// for later access
+    /// for later access.
+    ///
+    /// `path` may either be a single TOML file or a directory of `*.toml`
+    /// fragments (`conf.d`-style). Fragments are loaded in file-name order
+    /// and their `[[item]]` arrays are concatenated; if a later fragment
+    /// declares an item whose `alias` matches one from an earlier
+    /// fragment, it replaces it. This lets operators compose a base
+    /// config with host-specific overlays instead of maintaining one
+    /// monolithic file.
     pub fn load(path: PathBuf) -> Result<Self> {
-        let conf_deser = ConfigDeser::load(path)?;
+        let mut items : Vec<LogItemDeser> = Vec::new();
+        for fragment in Config::fragment_paths(&path)? {
+            for item in ConfigDeser::load(fragment)?.into_items() {
+                match items.iter_mut().find(|existing| existing.alias == item.alias) {
+                    Some(existing) => *existing = item,
+                    None => items.push(item),
+                }
+            }
+        }
 
         let mut l_items : Vec<LogItem> = Vec::new();
-        for lid in conf_deser.get_items() {
-            l_items.push(LogItem::try_from((*lid).clone())?);
+        for lid in items {
+            l_items.push(LogItem::try_from(lid)?);
         }
 
         // combines all aliases into one Vec for the /search endpoint
@@ -149,5 +422,194 @@ impl Config {
 
         Ok(Config { items: l_items, all_aliases : all_als })
     }
+
+    /// Resolves `path` into the ordered list of TOML fragments to load. A
+    /// directory is expanded into its `*.toml` entries sorted by file
+    /// name, so later, alphabetically-later overlays can override earlier
+    /// base fragments; a single file is used as-is.
+    fn fragment_paths(path: &Path) -> Result<Vec<PathBuf>> {
+        if path.is_dir() {
+            let mut fragments : Vec<PathBuf> = std::fs::read_dir(path)
+                .chain_err(|| "configuration directory could not be read")?
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|p| p.extension().and_then(OsStr::to_str) == Some("toml"))
+                .collect();
+            fragments.sort();
+            Ok(fragments)
+        } else {
+            Ok(vec![path.to_path_buf()])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates (and clears out) a scratch directory under the system temp
+    /// dir, unique to `label`, for tests that need real files on disk.
+    fn unique_temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aklog-config-test-{}", label));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        dir
+    }
+
+    #[test]
+    fn expand_env_vars_substitutes_braced_and_bare_forms() {
+        env::set_var("AKLOG_TEST_EXPAND_DIR", "/var/log/myapp");
+
+        assert_eq!(expand_env_vars("${AKLOG_TEST_EXPAND_DIR}/app.log"), "/var/log/myapp/app.log");
+        assert_eq!(expand_env_vars("$AKLOG_TEST_EXPAND_DIR/app.log"), "/var/log/myapp/app.log");
+
+        env::remove_var("AKLOG_TEST_EXPAND_DIR");
+    }
+
+    #[test]
+    fn expand_env_vars_keeps_unset_var_literal() {
+        env::remove_var("AKLOG_TEST_EXPAND_UNSET");
+
+        assert_eq!(expand_env_vars("${AKLOG_TEST_EXPAND_UNSET}/app.log"), "${AKLOG_TEST_EXPAND_UNSET}/app.log");
+    }
+
+    #[test]
+    fn is_glob_pattern_detects_metacharacters() {
+        assert!(is_glob_pattern("app-*.log"));
+        assert!(is_glob_pattern("app[0-9].log"));
+        assert!(!is_glob_pattern("/var/log/app.log"));
+    }
+
+    #[test]
+    fn expand_file_patterns_keeps_plain_path_with_no_match_as_literal() {
+        let dir = unique_temp_dir("literal-fallback");
+        let missing = dir.join("not-yet-created.log").display().to_string();
+
+        let files = expand_file_patterns("test", vec![missing.clone()]).unwrap();
+
+        assert_eq!(files, vec![missing]);
+    }
+
+    #[test]
+    fn expand_file_patterns_drops_glob_with_no_current_matches() {
+        let dir = unique_temp_dir("glob-no-match");
+        let pattern = dir.join("app-*.log").display().to_string();
+
+        let files = expand_file_patterns("test", vec![pattern]).unwrap();
+
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn expand_file_patterns_resolves_glob_matches() {
+        let dir = unique_temp_dir("glob-match");
+        std::fs::write(dir.join("app-1.log"), "").unwrap();
+        std::fs::write(dir.join("app-2.log"), "").unwrap();
+        let pattern = dir.join("app-*.log").display().to_string();
+
+        let files = expand_file_patterns("test", vec![pattern]).unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert!(files.iter().all(|f| f.contains("app-")));
+    }
+
+    fn test_item(regex: Regex, timestamp_strategy: TimestampStrategy) -> LogItem {
+        LogItem {
+            files : Vec::new(),
+            regex,
+            alias : "test".to_string(),
+            capture_names : Vec::new(),
+            aliases : Vec::new(),
+            timestamp_strategy,
+        }
+    }
+
+    #[test]
+    fn timestamp_millis_parses_raw_epoch_capture() {
+        let regex = Regex::new(r"(\w+) (\d+)").unwrap();
+        let item = test_item(regex.clone(), TimestampStrategy::Raw { group : TimestampGroup::Index(2) });
+        let caps = regex.captures("info 1700000000000").unwrap();
+
+        assert_eq!(item.timestamp_millis(&caps).unwrap(), 1_700_000_000_000);
+    }
+
+    #[test]
+    fn timestamp_millis_parses_named_capture_with_format() {
+        let regex = Regex::new(r"(?P<level>\w+) (?P<ts>[\d-]+ [\d:]+)").unwrap();
+        let item = test_item(regex.clone(), TimestampStrategy::Parsed {
+            group : TimestampGroup::Name("ts".to_string()),
+            format : "%Y-%m-%d %H:%M:%S".to_string(),
+        });
+        let caps = regex.captures("info 2026-07-27 10:00:00").unwrap();
+
+        assert!(item.timestamp_millis(&caps).is_ok());
+    }
+
+    #[test]
+    fn timestamp_millis_errors_on_format_mismatch() {
+        let regex = Regex::new(r"(?P<level>\w+) (?P<ts>\S+)").unwrap();
+        let item = test_item(regex.clone(), TimestampStrategy::Parsed {
+            group : TimestampGroup::Name("ts".to_string()),
+            format : "%Y-%m-%d %H:%M:%S".to_string(),
+        });
+        let caps = regex.captures("info not-a-date").unwrap();
+
+        assert!(item.timestamp_millis(&caps).is_err());
+    }
+
+    #[test]
+    fn validate_timestamp_group_rejects_whole_match_index() {
+        let regex = Regex::new(r"(\w+)").unwrap();
+
+        assert!(validate_timestamp_group(&regex, &TimestampGroup::Index(0), "test").is_err());
+    }
+
+    #[test]
+    fn validate_timestamp_group_accepts_existing_named_group() {
+        let regex = Regex::new(r"(?P<ts>\w+)").unwrap();
+
+        assert!(validate_timestamp_group(&regex, &TimestampGroup::Name("ts".to_string()), "test").is_ok());
+    }
+
+    #[test]
+    fn fragment_paths_returns_single_file_as_is() {
+        let file = PathBuf::from("/etc/aklog/config.toml");
+
+        assert_eq!(Config::fragment_paths(&file).unwrap(), vec![file]);
+    }
+
+    #[test]
+    fn fragment_paths_lists_sorted_toml_files_in_directory() {
+        let dir = unique_temp_dir("confd");
+        std::fs::write(dir.join("20-overlay.toml"), "").unwrap();
+        std::fs::write(dir.join("10-base.toml"), "").unwrap();
+        std::fs::write(dir.join("readme.txt"), "").unwrap();
+
+        let fragments = Config::fragment_paths(&dir).unwrap();
+
+        assert_eq!(fragments, vec![dir.join("10-base.toml"), dir.join("20-overlay.toml")]);
+    }
+
+    #[test]
+    fn config_load_merges_fragments_and_lets_later_alias_override_earlier() {
+        let dir = unique_temp_dir("merge");
+        std::fs::write(dir.join("10-base.toml"), "\
+            [[item]]\n\
+            file = \"/var/log/app.log\"\n\
+            regex = \"(\\\\w+)\"\n\
+            alias = \"app\"\n\
+        ").unwrap();
+        std::fs::write(dir.join("20-overlay.toml"), "\
+            [[item]]\n\
+            file = \"/var/log/app-overlay.log\"\n\
+            regex = \"(\\\\w+)\"\n\
+            alias = \"app\"\n\
+        ").unwrap();
+
+        let config = Config::load(dir).unwrap();
+
+        assert_eq!(config.items().len(), 1);
+        assert_eq!(config.items()[0].files(), &vec!["/var/log/app-overlay.log".to_string()]);
+    }
 }
 
@@ -4,9 +4,24 @@
 
 error_chain! {
     errors {
-        ConfigParseError(filename: String) {
+        ParseToml(filename: String, err: ::toml::de::Error) {
             description("configuration file could not be parsed"),
-            display("configuration file could not be parsed: '{}'", filename),
+            display("configuration file '{}' could not be parsed: {}", filename, err),
+        }
+
+        InvalidRegex(alias: String, err: String) {
+            description("log item regex could not be compiled"),
+            display("regex for item '{}' could not be compiled: {}", alias, err),
+        }
+
+        InvalidTimestampConfig(alias: String, message: String) {
+            description("log item timestamp configuration is invalid"),
+            display("timestamp configuration for item '{}' is invalid: {}", alias, message),
+        }
+
+        InvalidGlob(alias: String, pattern: String, err: String) {
+            description("log item file pattern is not a valid glob"),
+            display("file pattern '{}' for item '{}' is not a valid glob: {}", pattern, alias, err),
         }
     }
 }
@@ -0,0 +1,351 @@
+//! Subsystem that watches the configuration file and the log files it
+//! references, re-parsing and atomically swapping in new state so that
+//! `/search` and `/query` pick up changes without a restart.
+
+extern crate notify;
+extern crate arc_swap;
+
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+
+use arc_swap::ArcSwap;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+
+use config::Config;
+use error::*;
+
+/// Shared, hot-swappable handle to the current configuration.
+///
+/// Cloning this is cheap (it is itself an `Arc`) and every clone observes
+/// the most recently loaded `Config` once [`ConfigWatcher::spawn`] installs
+/// a new one.
+pub type SharedConfig = Arc<ArcSwap<Config>>;
+
+/// Inode and size of a watched log file, used to detect rotation or
+/// truncation that the filesystem watcher may not report directly (e.g. a
+/// `cp` instead of a `mv` during rotation).
+struct FileFingerprint {
+    inode : u64,
+    size : u64,
+}
+
+impl FileFingerprint {
+    fn of(path: &Path) -> Option<FileFingerprint> {
+        fs::metadata(path)
+            .ok()
+            .map(|md| FileFingerprint { inode: md.ino(), size: md.size() })
+    }
+
+    /// Whether `path` looks like it was rotated or truncated since this
+    /// fingerprint was taken: its inode changed, its size shrank, or it
+    /// disappeared entirely.
+    ///
+    /// This only compares against the fingerprint it is called on, so a
+    /// caller that keeps reusing an old fingerprint across many benign
+    /// writes would see its baseline size go stale as the file grows; see
+    /// [`refresh_fingerprint`] for the refresh-on-every-event counterpart
+    /// used by [`ConfigWatcher::spawn`] to avoid that.
+    fn changed(&self, path: &Path) -> bool {
+        match FileFingerprint::of(path) {
+            Some(now) => now.inode != self.inode || now.size < self.size,
+            None       => true,
+        }
+    }
+}
+
+/// Checks whether `path` (previously fingerprinted in `fingerprints`) looks
+/// rotated or truncated, then refreshes its stored fingerprint to the
+/// current state regardless of the outcome. Refreshing on every observed
+/// event, not just on reload, keeps the baseline size from going stale as
+/// a file grows between reloads, which a one-shot comparison against the
+/// fingerprint taken at startup/last-reload would not.
+///
+/// Returns `false` without touching `fingerprints` if `path` is not one of
+/// the files we are tracking.
+fn refresh_fingerprint(path: &Path, fingerprints: &mut HashMap<PathBuf, FileFingerprint>) -> bool {
+    if !fingerprints.contains_key(path) {
+        return false;
+    }
+
+    let current = FileFingerprint::of(path);
+    let rotated = match (&current, fingerprints.get(path)) {
+        (Some(current), Some(previous)) => current.inode != previous.inode || current.size < previous.size,
+        (None, _) => true,
+        (Some(_), None) => unreachable!("checked above"),
+    };
+
+    match current {
+        Some(current) => { fingerprints.insert(path.to_path_buf(), current); },
+        None => { fingerprints.remove(path); },
+    }
+
+    rotated
+}
+
+/// Extracts the path a `notify` event is about, if any. `Rename` reports
+/// the destination path, since that is what ends up on disk under a
+/// watched name.
+fn event_path(event: &DebouncedEvent) -> Option<PathBuf> {
+    match event {
+        DebouncedEvent::NoticeWrite(p)
+        | DebouncedEvent::NoticeRemove(p)
+        | DebouncedEvent::Create(p)
+        | DebouncedEvent::Write(p)
+        | DebouncedEvent::Chmod(p)
+        | DebouncedEvent::Remove(p) => Some(p.clone()),
+        DebouncedEvent::Rename(_, to) => Some(to.clone()),
+        DebouncedEvent::Rescan | DebouncedEvent::Error(_, _) => None,
+    }
+}
+
+/// Watches the config file (and every file in `LogItem::files`) for changes and
+/// keeps a [`SharedConfig`] up to date.
+pub struct ConfigWatcher {
+    path : PathBuf,
+    shared : SharedConfig,
+}
+
+impl ConfigWatcher {
+
+    /// Loads `path` once and wraps the result in a [`SharedConfig`] that
+    /// `spawn()` will keep fresh.
+    pub fn load(path: PathBuf) -> Result<ConfigWatcher> {
+        let config = Config::load(path.clone())?;
+        let shared = Arc::new(ArcSwap::from_pointee(config));
+        Ok(ConfigWatcher { path, shared })
+    }
+
+    /// Returns a cheap, clonable handle that always observes the latest
+    /// successfully loaded configuration.
+    pub fn shared(&self) -> SharedConfig {
+        self.shared.clone()
+    }
+
+    /// Whether `event_path` is part of the watched configuration: either
+    /// `self.path` itself, or, when `self.path` is a `conf.d`-style
+    /// directory, a `*.toml` fragment directly inside it. `notify` reports
+    /// directory-watch events with the *child's* path, never the
+    /// directory's, so a plain equality check against `self.path` would
+    /// never match a fragment edit.
+    fn is_config_path(&self, event_path: &Path) -> bool {
+        if event_path == self.path {
+            return true;
+        }
+
+        self.path.is_dir()
+            && event_path.parent() == Some(self.path.as_path())
+            && event_path.extension().and_then(OsStr::to_str) == Some("toml")
+    }
+
+    /// Spawns a background thread that reacts to filesystem events on the
+    /// config file and on every existing file in `LogItem::files`,
+    /// re-parsing the config and atomically swapping it in on success. A
+    /// config that fails to parse is logged and the previous, still-valid
+    /// config is kept in place. Log files are only stat'ed, never opened;
+    /// one that does not exist is skipped without a log message.
+    pub fn spawn(self) -> Result<()> {
+        let (tx, rx) = channel();
+        let mut watcher : RecommendedWatcher = Watcher::new(tx, Duration::from_secs(2))
+            .chain_err(|| "could not set up configuration watcher")?;
+
+        watcher.watch(&self.path, RecursiveMode::NonRecursive)
+            .chain_err(|| "could not watch configuration file")?;
+
+        let mut fingerprints = self.log_fingerprints();
+        for file in fingerprints.keys() {
+            // A missing log file at startup is not fatal, it may appear
+            // later and will then be picked up on the next reload.
+            let _ = watcher.watch(file, RecursiveMode::NonRecursive);
+        }
+
+        thread::spawn(move || {
+            let mut watcher = watcher;
+
+            loop {
+                let event = match rx.recv() {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!("configuration watcher channel closed: {}", e);
+                        break;
+                    },
+                };
+
+                let path = match event_path(&event) {
+                    Some(path) => path,
+                    None => continue,
+                };
+
+                if self.is_config_path(&path) {
+                    debug!("configuration file '{}' changed, reloading", path.display());
+                    self.reload();
+                    fingerprints = self.watch_new_files(&mut watcher, fingerprints);
+                } else if refresh_fingerprint(&path, &mut fingerprints) {
+                    debug!("detected log file rotation/truncation for '{}', reloading configuration", path.display());
+                    self.reload();
+                    fingerprints = self.watch_new_files(&mut watcher, fingerprints);
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Re-fingerprints the current log files and starts watching any that
+    /// were not already being watched, e.g. a glob that just matched a new
+    /// file or a rotation target that was just created.
+    fn watch_new_files(
+        &self,
+        watcher: &mut RecommendedWatcher,
+        previous: HashMap<PathBuf, FileFingerprint>,
+    ) -> HashMap<PathBuf, FileFingerprint> {
+        let current = self.log_fingerprints();
+        for file in current.keys() {
+            if !previous.contains_key(file) {
+                let _ = watcher.watch(file, RecursiveMode::NonRecursive);
+            }
+        }
+        current
+    }
+
+    fn log_fingerprints(&self) -> HashMap<PathBuf, FileFingerprint> {
+        self.shared.load()
+            .items()
+            .iter()
+            .flat_map(|item| item.files().iter())
+            .filter_map(|file| {
+                let path = PathBuf::from(file);
+                FileFingerprint::of(&path).map(|fp| (path, fp))
+            })
+            .collect()
+    }
+
+    fn reload(&self) {
+        match Config::load(self.path.clone()) {
+            Ok(new_config) => {
+                info!("configuration reloaded from '{}'", self.path.display());
+                self.shared.store(Arc::new(new_config));
+            },
+            Err(e) => {
+                warn!(
+                    "failed to reload configuration from '{}', keeping previous configuration: {}",
+                    self.path.display(), e
+                );
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates (and clears out) a scratch directory under the system temp
+    /// dir, unique to `label`, for tests that need real files on disk.
+    fn unique_temp_dir(label: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("aklog-watcher-test-{}", label));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).expect("create temp dir");
+        dir
+    }
+
+    #[test]
+    fn fingerprint_changed_detects_inode_change() {
+        let dir = unique_temp_dir("inode-change");
+        let path = dir.join("app.log");
+        std::fs::write(&path, "one").unwrap();
+        let fp = FileFingerprint::of(&path).unwrap();
+
+        // Simulate rotation via `mv`-then-recreate: a fresh file at the same
+        // path gets a new inode even if the content/size is unchanged.
+        std::fs::rename(&path, dir.join("app.log.1")).unwrap();
+        std::fs::write(&path, "one").unwrap();
+
+        assert!(fp.changed(&path));
+    }
+
+    #[test]
+    fn fingerprint_changed_detects_size_shrink() {
+        let dir = unique_temp_dir("size-shrink");
+        let path = dir.join("app.log");
+        std::fs::write(&path, "0123456789").unwrap();
+        let fp = FileFingerprint::of(&path).unwrap();
+
+        std::fs::write(&path, "01").unwrap();
+
+        assert!(fp.changed(&path));
+    }
+
+    #[test]
+    fn fingerprint_changed_detects_missing_file() {
+        let dir = unique_temp_dir("missing-file");
+        let path = dir.join("app.log");
+        std::fs::write(&path, "one").unwrap();
+        let fp = FileFingerprint::of(&path).unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(fp.changed(&path));
+    }
+
+    #[test]
+    fn fingerprint_changed_ignores_append_only_growth() {
+        let dir = unique_temp_dir("growth");
+        let path = dir.join("app.log");
+        std::fs::write(&path, "one").unwrap();
+        let fp = FileFingerprint::of(&path).unwrap();
+
+        std::fs::write(&path, "one two three").unwrap();
+
+        assert!(!fp.changed(&path));
+    }
+
+    #[test]
+    fn refresh_fingerprint_rebaselines_after_growth_so_later_shrink_is_still_caught() {
+        let dir = unique_temp_dir("refresh");
+        let path = dir.join("app.log");
+        std::fs::write(&path, "0123456789").unwrap();
+        let mut fingerprints = HashMap::new();
+        fingerprints.insert(path.clone(), FileFingerprint::of(&path).unwrap());
+
+        std::fs::write(&path, "01234567890123456789").unwrap();
+        assert!(!refresh_fingerprint(&path, &mut fingerprints));
+
+        // Shrinks back to a size still larger than the *original* baseline,
+        // but smaller than the grown size `refresh_fingerprint` just stored;
+        // the stale, one-shot comparison this replaced would have missed it.
+        std::fs::write(&path, "01234567890123").unwrap();
+        assert!(refresh_fingerprint(&path, &mut fingerprints));
+    }
+
+    #[test]
+    fn refresh_fingerprint_ignores_untracked_paths() {
+        let dir = unique_temp_dir("untracked");
+        let path = dir.join("app.log");
+        std::fs::write(&path, "one").unwrap();
+
+        assert!(!refresh_fingerprint(&path, &mut HashMap::new()));
+    }
+
+    #[test]
+    fn event_path_maps_each_variant() {
+        let p = PathBuf::from("/var/log/app.log");
+        let other = PathBuf::from("/var/log/app.log.1");
+
+        assert_eq!(event_path(&DebouncedEvent::NoticeWrite(p.clone())), Some(p.clone()));
+        assert_eq!(event_path(&DebouncedEvent::NoticeRemove(p.clone())), Some(p.clone()));
+        assert_eq!(event_path(&DebouncedEvent::Create(p.clone())), Some(p.clone()));
+        assert_eq!(event_path(&DebouncedEvent::Write(p.clone())), Some(p.clone()));
+        assert_eq!(event_path(&DebouncedEvent::Chmod(p.clone())), Some(p.clone()));
+        assert_eq!(event_path(&DebouncedEvent::Remove(p.clone())), Some(p.clone()));
+        assert_eq!(event_path(&DebouncedEvent::Rename(p.clone(), other.clone())), Some(other));
+        assert_eq!(event_path(&DebouncedEvent::Rescan), None);
+        assert_eq!(event_path(&DebouncedEvent::Error(notify::Error::WatchNotFound, None)), None);
+    }
+}